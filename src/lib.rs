@@ -1,8 +1,15 @@
 use easycurses::*;
 
-use ordered_float::NotNan;
-
+mod mesh;
+mod nn;
 mod prelude;
+mod settings;
+mod video;
+
+pub use mesh::Mesh;
+pub use nn::Net;
+pub use settings::Settings;
+pub use video::FrameRecorder;
 
 use crate::prelude::*;
 
@@ -26,8 +33,8 @@ impl Camera {
         Camera { pos, rot }
     }
 
-    pub fn update(&mut self, _: &mut EasyCurses, delta: f32, key: Option<Input>) {
-        let s = delta * 10.;
+    pub fn update(&mut self, _: &mut EasyCurses, delta: f32, key: Option<Input>, move_speed: f32) {
+        let s = delta * move_speed;
 
         if let Some(input) = key {
             match input {
@@ -80,11 +87,105 @@ pub trait Game {
 pub struct Term3D {
     pub backend: EasyCurses,
     pub cam: Camera,
+    pub meshes: Vec<Mesh>,
+    pub settings: Settings,
+    /// When set, drives the camera from `Net::feed` output on frames with no
+    /// keyboard input, letting a recorded or evolved policy fly the camera.
+    /// A pressed key (including Esc and `KeyResize`) still takes precedence.
+    pub agent: Option<Net>,
+    /// When set, every composited frame is also appended to this recorder.
+    pub recorder: Option<FrameRecorder>,
+    /// The fixed timestep `game.update`/`Camera::update` are advanced by,
+    /// independent of render cost.
+    pub fixed_dt: f32,
+    /// Caps how many fixed steps are taken per frame, so a long stall (e.g.
+    /// a `KeyResize`) can't spiral into an ever-growing update backlog.
+    pub max_substeps: u32,
 }
 
 impl Term3D {
     pub fn new() -> Self {
-        Self { backend: EasyCurses::initialize_system().unwrap(), cam: Camera::new((0., 0., 0.), (0., 0.))}
+        let cube = Mesh::new(
+            vec![
+                [-1., -1., -1.],
+                [1., -1., -1.],
+                [1., 1., -1.],
+                [-1., 1., -1.],
+                [-1., -1., 1.],
+                [1., -1., 1.],
+                [1., 1., 1.],
+                [-1., 1., 1.],
+            ],
+            vec![
+                vec![0, 1, 2, 3],
+                vec![4, 5, 6, 7],
+                vec![0, 1, 5, 4],
+                vec![2, 3, 7, 6],
+                vec![0, 3, 7, 4],
+                vec![1, 2, 6, 5],
+            ],
+        );
+
+        Self {
+            backend: EasyCurses::initialize_system().unwrap(),
+            cam: Camera::new((0., 0., 0.), (0., 0.)),
+            meshes: vec![cube],
+            settings: Settings::load("term3d.toml"),
+            agent: None,
+            recorder: None,
+            fixed_dt: 1. / 60.,
+            max_substeps: 5,
+        }
+    }
+
+    /// Offset from the camera to its nearest mesh vertex, used as a sensory
+    /// input for `agent`.
+    fn nearest_vertex_offset(&self) -> (f32, f32, f32) {
+        let mut nearest: Option<(f32, (f32, f32, f32))> = None;
+
+        for mesh in &self.meshes {
+            for v in &mesh.verts {
+                let offset = (
+                    v[0] - self.cam.pos.0,
+                    v[1] - self.cam.pos.1,
+                    v[2] - self.cam.pos.2,
+                );
+                let dist2 = offset.0 * offset.0 + offset.1 * offset.1 + offset.2 * offset.2;
+
+                if nearest.map_or(true, |(best, _)| dist2 < best) {
+                    nearest = Some((dist2, offset));
+                }
+            }
+        }
+
+        nearest.map(|(_, offset)| offset).unwrap_or((0., 0., 0.))
+    }
+
+    /// Maps `Net::feed`'s 10-way output (one per WASD+QE and arrow-key
+    /// action) to the corresponding key, picking the strongest activation.
+    fn action_from_net_output(out: &[f32]) -> Option<Input> {
+        const ACTIONS: [Input; 10] = [
+            Input::Character('q'),
+            Input::Character('e'),
+            Input::Character('w'),
+            Input::Character('a'),
+            Input::Character('s'),
+            Input::Character('d'),
+            Input::KeyUp,
+            Input::KeyDown,
+            Input::KeyLeft,
+            Input::KeyRight,
+        ];
+
+        // A conforming model's final layer isn't guaranteed to be exactly
+        // `ACTIONS.len()` wide, so only the outputs that map to an action
+        // are considered.
+        out.iter()
+            .take(ACTIONS.len())
+            .enumerate()
+            .filter(|(_, &v)| !v.is_nan())
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(i, _)| ACTIONS[i])
     }
 
     pub fn run<T: Game>(&mut self, game: &mut T) {
@@ -94,54 +195,18 @@ impl Term3D {
         self.backend.set_keypad_enabled(true);
         self.backend.set_echo(false);
 
-        let frame_target_duration = Duration::new(1, 0).checked_div(60).unwrap();
-
-        let verts = [
-            (-1., -1., -1.),
-            (1., -1., -1.),
-            (1., 1., -1.),
-            (-1., 1., -1.),
-            (-1., -1., 1.),
-            (1., -1., 1.),
-            (1., 1., 1.),
-            (-1., 1., 1.),
-        ];
-        // let edges = [
-        //     (0., 1.),
-        //     (1., 2.),
-        //     (2., 3.),
-        //     (3., 0.),
-        //     (4., 5.),
-        //     (5., 6.),
-        //     (6., 7.),
-        //     (7., 4.),
-        //     (0., 4.),
-        //     (1., 5.),
-        //     (2., 6.),
-        //     (3., 7.),
-        // ];
-        let faces = [
-            [0., 1., 2., 3.],
-            [4., 5., 6., 7.],
-            [0., 1., 5., 4.],
-            [2., 3., 7., 6.],
-            [0., 3., 7., 4.],
-            [1., 2., 6., 5.],
-        ];
+        let frame_target_duration = Duration::new(1, 0)
+            .checked_div(self.settings.framerate)
+            .unwrap();
 
-        const COLORS: &[Color] = &[
-            Color::Red,
-            Color::Green,
-            Color::Blue,
-            Color::Yellow,
-            Color::White,
-            Color::Magenta,
-        ];
+        let colors = self.settings.color_palette();
+        let background = self.settings.background_color();
 
         let (mut h, mut w) = self.backend.get_row_col_count();
         let (mut cx, mut cy) = (w as f32 / 2., h as f32 / 2.);
 
-        let mut delta_time: f32 = 0.;
+        let mut accumulator: f32 = 0.;
+        let mut last_loop = Instant::now();
 
         // Initialize game
         game.start(self);
@@ -150,6 +215,24 @@ impl Term3D {
             let top_of_loop = Instant::now();
 
             let key = self.backend.get_input();
+            let key = match (&self.agent, key) {
+                (Some(agent), None) => {
+                    let (nx, ny, nz) = self.nearest_vertex_offset();
+                    let sensors = [
+                        self.cam.pos.0,
+                        self.cam.pos.1,
+                        self.cam.pos.2,
+                        self.cam.rot.0,
+                        self.cam.rot.1,
+                        nx,
+                        ny,
+                        nz,
+                    ];
+                    Self::action_from_net_output(&agent.feed(&sensors))
+                }
+                (_, key) => key,
+            };
+
             if key == Some(Input::Character('\u{1b}')) {
                 break;
             } else if key == Some(Input::KeyResize) {
@@ -159,89 +242,88 @@ impl Term3D {
                 h = height;
                 cx = w as f32 / 2.;
                 cy = h as f32 / 2.;
-            } else {
-                self.cam.update(&mut self.backend, delta_time, key);
+                // Drop the update backlog a resize stall built up rather than
+                // burning through `max_substeps` worth of fixed steps at once.
+                accumulator = 0.;
+                last_loop = top_of_loop;
             }
 
-            game.update(self, delta_time);
+            accumulator += top_of_loop.duration_since(last_loop).as_secs_f32();
+            last_loop = top_of_loop;
 
-            let after_updates = Instant::now();
+            let mut substeps = 0;
+            while accumulator >= self.fixed_dt && substeps < self.max_substeps {
+                self.cam
+                    .update(&mut self.backend, self.fixed_dt, key, self.settings.move_speed);
+                game.update(self, self.fixed_dt);
+
+                accumulator -= self.fixed_dt;
+                substeps += 1;
+            }
+            if substeps == self.max_substeps {
+                accumulator = 0.;
+            }
 
             // clear screen
-            self.backend.set_color_pair(ColorPair::default());
+            self.backend.set_color_pair(ColorPair::new(background, background));
             for x in 0..w {
                 for y in 0..h {
                     Self::draw_cell(&mut self.backend, ' ', x, y);
                 }
             }
 
-            let mut vert_list = Vec::<[f32; 3]>::new();
-            let mut screen_coords = Vec::<IVec2>::new();
-
-            for (x, y, z) in &verts {
-                let (x, y, z) = (x - self.cam.pos.0, y - self.cam.pos.1, z - self.cam.pos.2);
-                let (mut x, z) = rotate_2d((x, z), self.cam.rot.1);
-                let (mut y, z) = rotate_2d((y, z), self.cam.rot.0);
-                vert_list.push([x, y, z]);
+            let mut zbuf = vec![f32::INFINITY; (w * h) as usize];
+            let mut cells = vec![background; (w * h) as usize];
 
-                let f = 200. / z;
-                x *= f;
-                y *= f;
-                screen_coords.push(IVec2::new((cx + x) as i32, (cy + y) as i32));
-            }
+            for mesh in &self.meshes {
+                let mut vert_list = Vec::<[f32; 3]>::new();
+                let mut screen_coords = Vec::<IVec2>::new();
 
-            let mut face_list = Vec::<Vec<IVec2>>::new();
-            let mut face_color = Vec::<Color>::new();
-            let mut depth = Vec::<f32>::new();
+                for [x, y, z] in &mesh.verts {
+                    let (x, y, z) = (x - self.cam.pos.0, y - self.cam.pos.1, z - self.cam.pos.2);
+                    let (mut x, z) = rotate_2d((x, z), self.cam.rot.1);
+                    let (mut y, z) = rotate_2d((y, z), self.cam.rot.0);
+                    vert_list.push([x, y, z]);
 
-            for i in 0..faces.len() {
-                let face = faces[i];
+                    let f = self.settings.fov / z;
+                    x *= f;
+                    y *= f;
+                    screen_coords.push(IVec2::new((cx + x) as i32, (cy + y) as i32));
+                }
 
-                let mut on_screen = false;
-                for &i in &face {
-                    let p = screen_coords[i as usize];
-                    if vert_list[i as usize][2] > 0. && p.x > 0 && p.x < w && p.y > 0 && p.y < h {
-                        on_screen = true;
-                        break;
+                for (face_idx, tri) in mesh.triangles().iter() {
+                    let mut on_screen = false;
+                    for &v in tri {
+                        let p = screen_coords[v];
+                        if vert_list[v][2] > 0. && p.x > 0 && p.x < w && p.y > 0 && p.y < h {
+                            on_screen = true;
+                            break;
+                        }
                     }
-                }
 
-                if on_screen {
-                    face_list.push(
-                        face.iter()
-                            .map(|&v| screen_coords[v as usize])
-                            .collect(),
-                    );
-                    face_color.push(COLORS[i]);
-
-                    // depth += [sum(sum(vert_list[j][k] for j in face)**2 for k in range(3))]
-                    depth.push((0..3).map(|k| {
-                        face.iter().map(|&j| {
-                            vert_list[j as usize][k as usize]
-                        }).sum::<f32>().powi(2)
-                    }).sum::<f32>());
+                    if on_screen {
+                        Self::draw_tri(
+                            &mut self.backend,
+                            &mut zbuf,
+                            &mut cells,
+                            w,
+                            colors[*face_idx % colors.len()],
+                            background,
+                            screen_coords[tri[0]],
+                            vert_list[tri[0]][2],
+                            screen_coords[tri[1]],
+                            vert_list[tri[1]][2],
+                            screen_coords[tri[2]],
+                            vert_list[tri[2]][2],
+                        );
+                    }
                 }
             }
 
-            let mut order = (0..face_list.len()).collect::<Vec<usize>>();
-            order.sort_by_key(|&k| NotNan::new(depth[k]).unwrap());
-            order.reverse();
-
-            for i in order {
-                Self::draw_tri(
-                    &mut self.backend,
-                    ColorPair::new(face_color[i], Color::Black),
-                    face_list[i][0],
-                    face_list[i][1],
-                    face_list[i][2],
-                );
-                Self::draw_tri(
-                    &mut self.backend,
-                    ColorPair::new(face_color[i], Color::Black),
-                    face_list[i][0],
-                    face_list[i][3],
-                    face_list[i][2],
-                );
+            if let Some(recorder) = &mut self.recorder {
+                recorder
+                    .write_frame(w, h, |x, y| video::color_to_rgb(cells[(y * w + x) as usize]))
+                    .ok();
             }
 
             let elapsed_this_frame = top_of_loop.elapsed();
@@ -250,10 +332,6 @@ impl Term3D {
             }
 
             self.backend.refresh();
-
-            let elapsed_after_updates = after_updates.elapsed();
-            delta_time = (elapsed_after_updates.as_secs() as f32)
-                + ((elapsed_after_updates.subsec_nanos() as f32) / 1000000000.0);
         }
     }
 
@@ -263,7 +341,22 @@ impl Term3D {
         e.print_char(c);
     }
 
-    fn draw_line_low(e: &mut EasyCurses, x0: i32, y0: i32, x1: i32, y1: i32) {
+    /// True when `step` (offset by `first_on`) falls inside the visible
+    /// run of a `period`-long dash/dot cycle.
+    fn step_visible(step: i32, period: i32, visible: i32, first_on: i32) -> bool {
+        (step + first_on).rem_euclid(period) < visible
+    }
+
+    fn draw_line_low_styled(
+        e: &mut EasyCurses,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        period: i32,
+        visible: i32,
+        first_on: i32,
+    ) {
         let dx = x1 - x0;
         let mut dy = y1 - y0;
         let mut yi = 1;
@@ -275,8 +368,10 @@ impl Term3D {
         let mut y = y0;
 
         e.set_color_pair(ColorPair::default());
-        for x in x0..x1 {
-            Term3D::draw_cell(e, '#', x, y);
+        for (step, x) in (x0..x1).enumerate() {
+            if Self::step_visible(step as i32, period, visible, first_on) {
+                Term3D::draw_cell(e, '#', x, y);
+            }
             if d > 0 {
                 y += yi;
                 d -= 2 * dx;
@@ -285,7 +380,16 @@ impl Term3D {
         }
     }
 
-    fn draw_line_high(e: &mut EasyCurses, x0: i32, y0: i32, x1: i32, y1: i32) {
+    fn draw_line_high_styled(
+        e: &mut EasyCurses,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        period: i32,
+        visible: i32,
+        first_on: i32,
+    ) {
         let mut dx = x1 - x0;
         let dy = y1 - y0;
         let mut xi = 1;
@@ -297,8 +401,10 @@ impl Term3D {
         let mut x = x0;
 
         e.set_color_pair(ColorPair::default());
-        for y in y0..y1 {
-            Term3D::draw_cell(e, '#', x, y);
+        for (step, y) in (y0..y1).enumerate() {
+            if Self::step_visible(step as i32, period, visible, first_on) {
+                Term3D::draw_cell(e, '#', x, y);
+            }
             if d > 0 {
                 x += xi;
                 d -= 2 * dy;
@@ -307,32 +413,52 @@ impl Term3D {
         }
     }
 
-    pub fn draw_line(e: &mut EasyCurses, x0: i32, y0: i32, x1: i32, y1: i32) {
+    /// Draws a line with a repeating `period`-long dash/dot cycle, `visible`
+    /// cells of which are drawn (offset by `first_on`). Pass `period ==
+    /// visible` for a solid line, which is what [`Self::draw_line`] does.
+    pub fn draw_line_styled(
+        e: &mut EasyCurses,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        period: i32,
+        visible: i32,
+        first_on: i32,
+    ) {
         if x0 == x1 {
-            for y in y0..=y1 {
-                Self::draw_cell(e, '|', x0, y);
+            for (step, y) in (y0..=y1).enumerate() {
+                if Self::step_visible(step as i32, period, visible, first_on) {
+                    Self::draw_cell(e, '|', x0, y);
+                }
             }
         } else if y0 == y1 {
-            for x in x0..=x1 {
-                Self::draw_cell(e, '-', x, y0);
+            for (step, x) in (x0..=x1).enumerate() {
+                if Self::step_visible(step as i32, period, visible, first_on) {
+                    Self::draw_cell(e, '-', x, y0);
+                }
             }
         } else {
             if (y1 - y0).abs() < (x1 - x0).abs() {
                 if x0 > x1 {
-                    Self::draw_line_low(e, x1, y1, x0, y0);
+                    Self::draw_line_low_styled(e, x1, y1, x0, y0, period, visible, first_on);
                 } else {
-                    Self::draw_line_low(e, x0, y0, x1, y1);
+                    Self::draw_line_low_styled(e, x0, y0, x1, y1, period, visible, first_on);
                 }
             } else {
                 if y0 > y1 {
-                    Self::draw_line_high(e, x1, y1, x0, y0);
+                    Self::draw_line_high_styled(e, x1, y1, x0, y0, period, visible, first_on);
                 } else {
-                    Self::draw_line_high(e, x0, y0, x1, y1);
+                    Self::draw_line_high_styled(e, x0, y0, x1, y1, period, visible, first_on);
                 }
             }
         }
     }
 
+    pub fn draw_line(e: &mut EasyCurses, x0: i32, y0: i32, x1: i32, y1: i32) {
+        Self::draw_line_styled(e, x0, y0, x1, y1, 1, 1, 0);
+    }
+
     /// # Returns
     /// (minimum x, maximum x, minimum y, maximum y)
     pub fn tri_bounding_box(v1: IVec2, v2: IVec2, v3: IVec2) -> (i32, i32, i32, i32) {
@@ -358,24 +484,43 @@ impl Term3D {
         (min_x, max_x, min_y, max_y)
     }
 
-    pub fn draw_tri(e: &mut EasyCurses, color: ColorPair, v1: IVec2, v2: IVec2, v3: IVec2) {
+    /// Rasterizes a triangle, depth-testing each covered cell against
+    /// `zbuf` (row-major, `buf_w` wide) using the per-vertex camera-space
+    /// depths `z1`/`z2`/`z3` interpolated with the same barycentric `s`/`t`
+    /// weights used to fill the triangle.
+    pub fn draw_tri(
+        e: &mut EasyCurses,
+        zbuf: &mut [f32],
+        cells: &mut [Color],
+        buf_w: i32,
+        fg: Color,
+        bg: Color,
+        v1: IVec2,
+        z1: f32,
+        v2: IVec2,
+        z2: f32,
+        v3: IVec2,
+        z3: f32,
+    ) {
         // calculate triangle bounding box
         let (minx, maxx, miny, maxy) = {
             let (minx, maxx, miny, maxy) = Self::tri_bounding_box(v1, v2, v3);
-            // Clip box against render target bounds
+            // Clip box against render target bounds. `zbuf`/`cells` are
+            // sized exactly `emax_x * emax_y`, so the clamp must stop at
+            // the last valid column/row, not at `emax_x`/`emax_y` itself.
             let (emax_y, emax_x) = e.get_row_col_count();
             (
-                min(emax_x, max(0, minx)),
-                min(emax_x, max(0, maxx)),
-                min(emax_y, max(0, miny)),
-                min(emax_y, max(0, maxy)),
+                min(emax_x - 1, max(0, minx)),
+                min(emax_x - 1, max(0, maxx)),
+                min(emax_y - 1, max(0, miny)),
+                min(emax_y - 1, max(0, maxy)),
             )
         };
 
         let vs1 = IVec2::new(v2.x - v1.x, v2.y - v1.y);
         let vs2 = IVec2::new(v3.x - v1.x, v3.y - v1.y);
 
-        e.set_color_pair(color);
+        e.set_color_pair(ColorPair::new(fg, bg));
         for x in minx..=maxx {
             for y in miny..=maxy {
                 let q = IVec2::new(x - v1.x, y - v1.y);
@@ -384,7 +529,14 @@ impl Term3D {
                 let t = vs1.perp_dot_product(&q) / vs1.perp_dot_product(&vs2);
 
                 if (s >= 0.) && (t >= 0.) && (s + t <= 1.) {
-                    Self::draw_cell(e, '#', x, y);
+                    let idx = (y * buf_w + x) as usize;
+                    let z = z1 + (z2 - z1) * s + (z3 - z1) * t;
+
+                    if z < zbuf[idx] {
+                        zbuf[idx] = z;
+                        cells[idx] = fg;
+                        Self::draw_cell(e, '#', x, y);
+                    }
                 }
             }
         }
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::Path;
+
+/// A 3D mesh: a vertex list plus a list of faces (each face a list of
+/// 1-or-more-sided vertex indices into `verts`).
+pub struct Mesh {
+    pub verts: Vec<[f32; 3]>,
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl Mesh {
+    pub fn new(verts: Vec<[f32; 3]>, faces: Vec<Vec<usize>>) -> Mesh {
+        Mesh { verts, faces }
+    }
+
+    /// Loads a mesh from a minimal Wavefront OBJ file.
+    ///
+    /// `v x y z` lines give vertices and `f i j k ...` lines give faces
+    /// (1-indexed; `/`-separated texture and normal indices are ignored).
+    pub fn from_obj<P: AsRef<Path>>(path: P) -> std::io::Result<Mesh> {
+        let text = fs::read_to_string(path)?;
+
+        let mut verts = Vec::new();
+        let mut faces = Vec::new();
+
+        for line in text.lines() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let mut xyz = tokens.filter_map(|t| t.parse::<f32>().ok());
+                    let x = xyz.next().unwrap_or(0.);
+                    let y = xyz.next().unwrap_or(0.);
+                    let z = xyz.next().unwrap_or(0.);
+                    verts.push([x, y, z]);
+                }
+                Some("f") => {
+                    // `i - 1`: OBJ face indices are 1-indexed; `checked_sub`
+                    // drops a malformed `0` index instead of underflowing.
+                    let face = tokens
+                        .filter_map(|t| t.split('/').next())
+                        .filter_map(|i| i.parse::<usize>().ok())
+                        .filter_map(|i| i.checked_sub(1))
+                        .collect::<Vec<usize>>();
+                    faces.push(face);
+                }
+                _ => {}
+            }
+        }
+
+        // Drop faces referencing a vertex index out of range for the
+        // parsed vertex list, e.g. from a malformed or truncated file.
+        faces.retain(|face| face.iter().all(|&i| i < verts.len()));
+
+        Ok(Mesh { verts, faces })
+    }
+
+    /// Fan-triangulates every face (`v0,v1,v2`, `v0,v2,v3`, ...) so the
+    /// renderer only ever has to deal with triangles, pairing each
+    /// triangle with the index of the face it came from so callers can
+    /// keep coloring by face rather than by triangle.
+    pub fn triangles(&self) -> Vec<(usize, [usize; 3])> {
+        let mut tris = Vec::new();
+        for (face_idx, face) in self.faces.iter().enumerate() {
+            for i in 1..face.len().saturating_sub(1) {
+                tris.push((face_idx, [face[0], face[i], face[i + 1]]));
+            }
+        }
+        tris
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct RawNet {
+    config: Vec<usize>,
+    weights: Vec<(Vec<f32>, usize, usize)>,
+}
+
+struct Layer {
+    weights: Vec<f32>,
+    rows: usize,
+    cols: usize,
+}
+
+/// A dense feedforward network for scripting camera/agent behavior: maps a
+/// camera's state to the same actions a player would feed through WASD and
+/// the arrow keys, so a recorded or evolved policy can fly the camera
+/// around a scene.
+pub struct Net {
+    layers: Vec<Layer>,
+}
+
+impl Net {
+    /// Loads a network from a JSON model of the form
+    /// `{"config":[in,h1,...,out], "weights":[[flat_matrix, cols, rows], ...]}`.
+    /// Each matrix is row-major `rows` x `cols`, with one extra column per
+    /// row acting as the bias.
+    pub fn from_json<P: AsRef<Path>>(path: P) -> std::io::Result<Net> {
+        let text = fs::read_to_string(path)?;
+        let raw: RawNet = serde_json::from_str(&text)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        let mut prev_size = *raw
+            .config
+            .first()
+            .ok_or_else(|| invalid_model("`config` must not be empty"))?;
+
+        let mut layers = Vec::with_capacity(raw.weights.len());
+        for (weights, cols, rows) in raw.weights {
+            if cols != prev_size + 1 {
+                return Err(invalid_model(&format!(
+                    "layer expects {} input columns (prev layer size + bias), got {}",
+                    prev_size + 1,
+                    cols
+                )));
+            }
+            if weights.len() != rows * cols {
+                return Err(invalid_model(&format!(
+                    "layer matrix has {} weights, expected rows * cols = {}",
+                    weights.len(),
+                    rows * cols
+                )));
+            }
+
+            layers.push(Layer { weights, rows, cols });
+            prev_size = rows;
+        }
+
+        Ok(Net { layers })
+    }
+
+    /// Feeds `input` through every layer, computing `out = tanh(W * [input, 1.0])`
+    /// layer by layer. NaN outputs are clamped to 0. `input` is padded with
+    /// zeros or truncated to match the first layer's expected width, so a
+    /// model whose `config[0]` doesn't match the caller's sensor count
+    /// can't index out of bounds.
+    pub fn feed(&self, input: &[f32]) -> Vec<f32> {
+        let mut activations = input.to_vec();
+
+        for layer in &self.layers {
+            let mut biased = activations;
+            biased.resize(layer.cols - 1, 0.);
+            biased.push(1.0);
+
+            let mut out = Vec::with_capacity(layer.rows);
+            for row in 0..layer.rows {
+                let sum: f32 = (0..layer.cols)
+                    .map(|col| layer.weights[row * layer.cols + col] * biased[col])
+                    .sum();
+                let v = sum.tanh();
+                out.push(if v.is_nan() { 0. } else { v });
+            }
+
+            activations = out;
+        }
+
+        activations
+    }
+}
+
+fn invalid_model(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
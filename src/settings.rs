@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::Path;
+
+use easycurses::Color;
+use serde::Deserialize;
+
+/// Tunables normally hardcoded in `Term3D`/`Camera`, loaded from a
+/// `term3d.toml` file. Missing fields fall back to their hardcoded
+/// defaults, and a missing or unparsable file falls back to
+/// [`Settings::default`] entirely, so existing users are unaffected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub framerate: u32,
+    pub move_speed: f32,
+    pub fov: f32,
+    pub background: String,
+    pub colors: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            framerate: 60,
+            move_speed: 10.,
+            fov: 200.,
+            background: "black".to_string(),
+            colors: ["red", "green", "blue", "yellow", "white", "magenta"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings from `path`, falling back to [`Settings::default`]
+    /// when the file is absent or fails to parse. A `framerate` of `0` or
+    /// an empty `colors` list is otherwise-valid TOML that would panic
+    /// downstream (a zero-length frame duration, a `% 0`), so those fields
+    /// fall back to their defaults individually rather than trusting the
+    /// file blindly.
+    pub fn load<P: AsRef<Path>>(path: P) -> Settings {
+        let mut settings: Settings = fs::read_to_string(path)
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+
+        let defaults = Settings::default();
+        if settings.framerate == 0 {
+            settings.framerate = defaults.framerate;
+        }
+        if settings.colors.is_empty() {
+            settings.colors = defaults.colors;
+        }
+
+        settings
+    }
+
+    /// The face color palette, parsed from `colors`.
+    pub fn color_palette(&self) -> Vec<Color> {
+        self.colors.iter().map(|name| color_from_name(name)).collect()
+    }
+
+    /// The background color, parsed from `background`.
+    pub fn background_color(&self) -> Color {
+        color_from_name(&self.background)
+    }
+}
+
+fn color_from_name(name: &str) -> Color {
+    match name.to_lowercase().as_str() {
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "blue" => Color::Blue,
+        "yellow" => Color::Yellow,
+        "white" => Color::White,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        _ => Color::Black,
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use easycurses::Color;
+
+/// Writes composited frames to a `.y4m` (YUV4MPEG2) stream so a session can
+/// be exported for sharing or offline transcoding without screen-capture
+/// tooling.
+pub struct FrameRecorder {
+    file: File,
+    cols: i32,
+    rows: i32,
+}
+
+impl FrameRecorder {
+    /// Creates `path` and writes the stream header.
+    pub fn start<P: AsRef<Path>>(path: P, cols: i32, rows: i32, framerate: u32) -> io::Result<FrameRecorder> {
+        let mut file = File::create(path)?;
+        writeln!(file, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", cols, rows, framerate)?;
+        Ok(FrameRecorder { file, cols, rows })
+    }
+
+    /// Writes one frame, sampling `cell_color(x, y)` for every cell of a
+    /// `src_cols` x `src_rows` source (the terminal's *current* size, which
+    /// may have changed since `start()` on a `KeyResize`) and converting to
+    /// BT.601 YUV planes. The stream's frame size is fixed at `start()`, so
+    /// the source is cropped or padded with black to fit it.
+    pub fn write_frame<F: Fn(i32, i32) -> (u8, u8, u8)>(
+        &mut self,
+        src_cols: i32,
+        src_rows: i32,
+        cell_color: F,
+    ) -> io::Result<()> {
+        let n = (self.cols * self.rows) as usize;
+        let mut y_plane = Vec::with_capacity(n);
+        let mut u_plane = Vec::with_capacity(n);
+        let mut v_plane = Vec::with_capacity(n);
+
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                let (r, g, b) = if x < src_cols && y < src_rows {
+                    cell_color(x, y)
+                } else {
+                    (0, 0, 0)
+                };
+                let (r, g, b) = (r as f32, g as f32, b as f32);
+
+                y_plane.push(clamp_u8(0.299 * r + 0.587 * g + 0.114 * b));
+                u_plane.push(clamp_u8(128. - 0.169 * r - 0.331 * g + 0.5 * b));
+                v_plane.push(clamp_u8(128. + 0.5 * r - 0.419 * g - 0.081 * b));
+            }
+        }
+
+        writeln!(self.file, "FRAME")?;
+        self.file.write_all(&y_plane)?;
+        self.file.write_all(&u_plane)?;
+        self.file.write_all(&v_plane)?;
+
+        Ok(())
+    }
+}
+
+fn clamp_u8(v: f32) -> u8 {
+    v.round().max(0.).min(255.) as u8
+}
+
+/// Maps a palette `Color` to an RGB triple for sampling into a recorded frame.
+pub fn color_to_rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Red => (255, 0, 0),
+        Color::Green => (0, 255, 0),
+        Color::Blue => (0, 0, 255),
+        Color::Yellow => (255, 255, 0),
+        Color::White => (255, 255, 255),
+        Color::Magenta => (255, 0, 255),
+        Color::Cyan => (0, 255, 255),
+        _ => (0, 0, 0),
+    }
+}